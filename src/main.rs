@@ -2,20 +2,86 @@ extern crate regex;
 
 mod parser;
 use crate::parser::calculator::*;
+use crate::parser::error::CalcError;
 use std::env;
+use std::io::{self, BufRead, Write};
 
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
-    if args.len() == 1usize
+    match args.len()
     {
-        match evaluate(&args[0])
-        {
-            Ok(r) => println!("Result: {}", r),
-            Err(e) => println!("Error evaluating the expression: {}", e)
+        0 => repl(),
+        1 => print_result(evaluate(&args[0]).map(|r| r.to_string()), &args[0]),
+        2 => run_subcommand(&args[0], &args[1]),
+        _ => println!("Please provide an expression to evaluate as one string without spaces. Example: 2*5"),
+    }
+}
+
+fn run_subcommand(command: &str, expression: &str)
+{
+    match command
+    {
+        "exact" => {
+            let result = evaluate_exact(&expression.to_string()).map(|r| format!("{}/{}", r.numerator, r.denominator));
+            print_result(result, expression);
         }
+        "value" => {
+            let result = evaluate_value(&expression.to_string()).map(|v| match v {
+                Value::Int(i) => i.to_string(),
+                Value::Float(f) => f.to_string(),
+                Value::Boolean(b) => b.to_string(),
+            });
+            print_result(result, expression);
+        }
+        "solve" => {
+            let result = solve(expression).map(|s| format!("{:?}", s));
+            print_result(result, expression);
+        }
+        _ => println!("Unknown subcommand '{}'. Use 'exact', 'value' or 'solve'.", command),
     }
-    else
+}
+
+fn print_result(result: Result<String, CalcError>, expression: &str)
+{
+    match result
     {
-        println!("Please provide an expression to evaluate as one string without spaces. Example: 2*5");
+        Ok(r) => println!("Result: {}", r),
+        Err(e) => println!("{}", e.render(expression)),
+    }
+}
+
+/// A REPL that keeps a `Context` alive across lines, so assignments like `x = 3+4` can be
+/// reused by later expressions. `:get <name>` inspects a previously stored variable.
+fn repl()
+{
+    let mut ctx = Context::new();
+    let stdin = io::stdin();
+    print!("> ");
+    io::stdout().flush().ok();
+    for line in stdin.lock().lines()
+    {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix(":get ")
+        {
+            match ctx.get(name.trim())
+            {
+                Some(value) => println!("{} = {}", name.trim(), value),
+                None => println!("'{}' is not defined", name.trim()),
+            }
+        }
+        else if !line.is_empty()
+        {
+            match evaluate_in(line, &mut ctx)
+            {
+                Ok(r) => println!("{}", r),
+                Err(e) => println!("{}", e.render(line)),
+            }
+        }
+        print!("> ");
+        io::stdout().flush().ok();
     }
 }
@@ -1,5 +1,69 @@
+pub mod error {
+    use std::fmt;
+    use std::ops::Range;
+
+    #[derive(Debug, PartialEq, Clone)]
+    pub enum CalcError {
+        UnknownToken { text: String, span: Range<usize> },
+        MismatchedParen { span: Range<usize> },
+        UnexpectedEnd,
+        DivisionByZero,
+        EmptyExpression,
+        Message(String),
+    }
+
+    impl CalcError {
+        /// The span into the original expression the error points at, if any. Offsets count
+        /// characters, not bytes, so they differ from byte offsets for non-ASCII input.
+        pub fn span(&self) -> Option<Range<usize>> {
+            match self {
+                CalcError::UnknownToken { span, .. } => Some(span.clone()),
+                CalcError::MismatchedParen { span } => Some(span.clone()),
+                _ => None,
+            }
+        }
+
+        /// Render this error as a caret-underlined snippet of `source`, e.g.
+        /// ```text
+        /// 2+$
+        ///   ^
+        /// unknown token '$' at position 2
+        /// ```
+        pub fn render(&self, source: &str) -> String {
+            match self.span() {
+                Some(span) => {
+                    let width = (span.end - span.start).max(1);
+                    format!("{}\n{}{}\n{}", source, " ".repeat(span.start), "^".repeat(width), self)
+                }
+                None => format!("{}\n{}", source, self),
+            }
+        }
+    }
+
+    impl fmt::Display for CalcError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CalcError::UnknownToken { text, span } => {
+                    write!(f, "unknown token '{}' at position {}", text, span.start)
+                }
+                CalcError::MismatchedParen { span } => {
+                    write!(f, "mismatched parenthesis at position {}", span.start)
+                }
+                CalcError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+                CalcError::DivisionByZero => write!(f, "division by zero"),
+                CalcError::EmptyExpression => write!(f, "expression is empty"),
+                CalcError::Message(m) => write!(f, "{}", m),
+            }
+        }
+    }
+
+    impl std::error::Error for CalcError {}
+}
+
 pub mod lexer {
     use regex::Regex;
+    use std::ops::Range;
+    use crate::parser::error::CalcError;
 
     #[derive(PartialEq, Debug, Copy, Clone)]
     pub enum TokenType {
@@ -9,6 +73,16 @@ pub mod lexer {
         Divide,
         Power,
         Number,
+        Identifier,
+        Function,
+        Comma,
+        Equal,
+        NotEqual,
+        Less,
+        LessEqual,
+        Greater,
+        GreaterEqual,
+        Assign,
         OpeningParenthesis,
         ClosingParaenthesis
     }
@@ -17,12 +91,14 @@ pub mod lexer {
         Left,
         Right,
     }
-    #[derive(Debug, PartialEq, Copy, Clone)]
+    #[derive(Debug, PartialEq, Clone)]
     pub struct Token {
         r#type: self::TokenType,
         value: f64,
+        name: String,
         precedence: u8,
         associativity: self::Associativity,
+        span: Range<usize>,
     }
     #[derive(PartialEq)]
     enum ParserState {
@@ -30,77 +106,187 @@ pub mod lexer {
         OPERATOR,
         PARENTHESIS,
         Number,
+        Identifier,
+    }
+
+    /// The built-in function names the calculator understands. Kept in sync with
+    /// `calculator::function_arity` so the lexer can tell a function call (`sqrt(`) apart
+    /// from a plain variable immediately followed by a parenthesized expression (`r(2+3)`,
+    /// meaning `r*(2+3)`).
+    const KNOWN_FUNCTIONS: &[&str] = &["sqrt", "sin", "cos", "tan", "ln", "abs", "max", "min"];
+
+    pub fn is_known_function(name: &str) -> bool {
+        KNOWN_FUNCTIONS.contains(&name)
     }
 
-    pub fn tokenize(content: &String) -> Result<Vec<Token>, &'static str> {
+    pub fn tokenize(content: &String) -> Result<Vec<Token>, CalcError> {
+        if content.is_empty() {
+            return Err(CalcError::EmptyExpression);
+        }
+
+        let total_chars = content.chars().count();
         let mut v: Vec<Token> = Vec::new();
         let mut state = ParserState::NONE;
         let mut buffer = String::new();
-        for c in content.chars() {
-            if c.is_alphabetic() {
-                let error = format!("Invalid character: {}", c);
-                return Err(Box::leak(error.into_boxed_str()));
-            } else if state == ParserState::Number {
+        let mut buffer_start = 0usize;
+        for (i, c) in content.chars().enumerate() {
+            if state == ParserState::Number {
                 if c.is_numeric() || c == '.' {
                     buffer.push(c);
-                } else if c == '^' || c == '*' || c == '/' || c == '-' || c == '+'{
-                    v.push(Token::new(&buffer).unwrap());
+                } else if c == '^' || c == '*' || c == '/' || c == '-' || c == '+' || c == ',' || c == '=' || c == '!' || c == '<' || c == '>' {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
                     buffer = String::new();
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::OPERATOR;
                 } else if c == '(' || c == ')' {
-                    v.push(Token::new(&buffer).unwrap());
+                    v.push(Token::new(&buffer, buffer_start..i)?);
                     buffer = String::new();
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::PARENTHESIS;
+                } else if c.is_alphabetic() || c == '_' {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
+                    buffer = String::new();
+                    buffer.push(c);
+                    buffer_start = i;
+                    state = ParserState::Identifier;
+                } else if !c.is_whitespace() {
+                    return Err(CalcError::UnknownToken { text: c.to_string(), span: i..i + 1 });
+                }
+            } else if state == ParserState::Identifier {
+                if c.is_alphanumeric() || c == '_' {
+                    buffer.push(c);
+                } else if c == '^' || c == '*' || c == '/' || c == '-' || c == '+' || c == ',' || c == '=' || c == '!' || c == '<' || c == '>' {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
+                    buffer = String::new();
+                    buffer.push(c);
+                    buffer_start = i;
+                    state = ParserState::OPERATOR;
+                } else if c == '(' {
+                    if is_known_function(&buffer) {
+                        v.push(Token::function(&buffer, buffer_start..i));
+                    } else {
+                        v.push(Token::new(&buffer, buffer_start..i)?);
+                    }
+                    buffer = String::new();
+                    buffer.push(c);
+                    buffer_start = i;
+                    state = ParserState::PARENTHESIS;
+                } else if c == ')' {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
+                    buffer = String::new();
+                    buffer.push(c);
+                    buffer_start = i;
+                    state = ParserState::PARENTHESIS;
+                } else if !c.is_whitespace() {
+                    return Err(CalcError::UnknownToken { text: c.to_string(), span: i..i + 1 });
                 }
             } else if state == ParserState::OPERATOR {
-                if c == '-' || c.is_numeric() {
-                    v.push(Token::new(&buffer).unwrap());
+                if c == '=' && (buffer == "=" || buffer == "!" || buffer == "<" || buffer == ">") {
+                    buffer.push(c);
+                } else if c == '-' || c.is_numeric() {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
                     buffer = String::new();
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::Number;
+                } else if c.is_alphabetic() || c == '_' {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
+                    buffer = String::new();
+                    buffer.push(c);
+                    buffer_start = i;
+                    state = ParserState::Identifier;
                 } else if c == '(' || c == ')' {
-                    v.push(Token::new(&buffer).unwrap());
+                    v.push(Token::new(&buffer, buffer_start..i)?);
                     buffer = String::new();
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::PARENTHESIS;
+                } else if !c.is_whitespace() {
+                    return Err(CalcError::UnknownToken { text: c.to_string(), span: i..i + 1 });
                 }
             } else if state == ParserState::PARENTHESIS{
                 if c == '-' || c.is_numeric(){
-                    v.push(Token::new(&buffer).unwrap());
+                    v.push(Token::new(&buffer, buffer_start..i)?);
                     buffer = String::new();
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::Number;
-                } else if c == '^' || c == '*' || c == '/' || c == '-' || c == '+' {
-                    v.push(Token::new(&buffer).unwrap());
+                } else if c.is_alphabetic() || c == '_' {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
                     buffer = String::new();
                     buffer.push(c);
+                    buffer_start = i;
+                    state = ParserState::Identifier;
+                } else if c == '^' || c == '*' || c == '/' || c == '-' || c == '+' || c == ',' || c == '=' || c == '!' || c == '<' || c == '>' {
+                    v.push(Token::new(&buffer, buffer_start..i)?);
+                    buffer = String::new();
+                    buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::OPERATOR;
                 } else if c == '(' || c == ')'{
-                    v.push(Token::new(&buffer).unwrap());
+                    v.push(Token::new(&buffer, buffer_start..i)?);
                     buffer = String::new();
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::PARENTHESIS;
+                } else if !c.is_whitespace() {
+                    return Err(CalcError::UnknownToken { text: c.to_string(), span: i..i + 1 });
                 }
             } else if state == ParserState::NONE {
                 if c.is_numeric() || c == '-' {
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::Number;
                 }
+                else if c.is_alphabetic() || c == '_' {
+                    buffer.push(c);
+                    buffer_start = i;
+                    state = ParserState::Identifier;
+                }
                 else if c == '(' {
                     buffer.push(c);
+                    buffer_start = i;
                     state = ParserState::PARENTHESIS;
                 }
+                else if !c.is_whitespace() {
+                    return Err(CalcError::UnknownToken { text: c.to_string(), span: i..i + 1 });
+                }
             }
         }
-        if !buffer.is_empty() && (state == ParserState::Number || state == ParserState::PARENTHESIS) {
-            v.push(Token::new(&buffer).unwrap());
+        if !buffer.is_empty() && (state == ParserState::Number || state == ParserState::PARENTHESIS || state == ParserState::Identifier) {
+            v.push(Token::new(&buffer, buffer_start..total_chars)?);
         } else {
-            return Err("Invalid end of expression");
+            return Err(CalcError::UnexpectedEnd);
         }
 
-        Ok(v)
+        Ok(insert_implicit_multiplication(v))
+    }
+
+    fn insert_implicit_multiplication(tokens: Vec<Token>) -> Vec<Token> {
+        let mut result: Vec<Token> = Vec::with_capacity(tokens.len());
+        for t in tokens.into_iter() {
+            if let Some(prev) = result.last() {
+                let implies_product = match (prev.get_type(), t.get_type()) {
+                    (TokenType::Number, TokenType::Identifier)
+                    | (TokenType::Number, TokenType::Function)
+                    | (TokenType::Number, TokenType::OpeningParenthesis)
+                    | (TokenType::Identifier, TokenType::OpeningParenthesis)
+                    | (TokenType::ClosingParaenthesis, TokenType::Number)
+                    | (TokenType::ClosingParaenthesis, TokenType::Identifier)
+                    | (TokenType::ClosingParaenthesis, TokenType::Function)
+                    | (TokenType::ClosingParaenthesis, TokenType::OpeningParenthesis) => true,
+                    _ => false,
+                };
+                if implies_product {
+                    let at = t.get_span().start;
+                    result.push(Token::new("*", at..at).expect("'*' is always a valid token"));
+                }
+            }
+            result.push(t);
+        }
+        result
     }
 
     impl Token {
@@ -111,66 +297,180 @@ pub mod lexer {
            }
         }
 
-        pub fn new(content: &str) -> Result<Token, &'static str> {
+        pub fn is_comparison(&self) -> bool {
+           match self.get_type() {
+            TokenType::Equal | TokenType::NotEqual | TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual => true,
+            _ => false,
+           }
+        }
+
+        pub fn new(content: &str, span: Range<usize>) -> Result<Token, CalcError> {
             if Regex::new(r"^\+$").unwrap().is_match(content) {
                 Ok(Token {
                     r#type: TokenType::Plus,
                     value: 0f64,
+                    name: String::new(),
                     precedence: 2,
                     associativity: Associativity::Left,
+                    span: span.clone(),
                 })
             } else if Regex::new(r"^\-$").unwrap().is_match(content) {
                 Ok(Token {
                     r#type: TokenType::Minus,
                     value: 0f64,
+                    name: String::new(),
                     precedence: 2,
                     associativity: Associativity::Left,
+                    span: span.clone(),
                 })
             } else if Regex::new(r"^\*$").unwrap().is_match(content) {
                 Ok(Token {
                     r#type: TokenType::Multiply,
                     value: 0f64,
+                    name: String::new(),
                     precedence: 3,
                     associativity: Associativity::Left,
+                    span: span.clone(),
                 })
             } else if Regex::new(r"^/$").unwrap().is_match(content) {
                 Ok(Token {
                     r#type: TokenType::Divide,
                     value: 0f64,
+                    name: String::new(),
                     precedence: 3,
                     associativity: Associativity::Left,
+                    span: span.clone(),
                 })
             } else if Regex::new(r"^\^$").unwrap().is_match(content) {
                 Ok(Token {
                     r#type: TokenType::Power,
                     value: 0f64,
+                    name: String::new(),
                     precedence: 4,
                     associativity: Associativity::Right,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^,$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::Comma,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 0,
+                    associativity: Associativity::Right,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^==$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::Equal,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 1,
+                    associativity: Associativity::Left,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^!=$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::NotEqual,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 1,
+                    associativity: Associativity::Left,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^<=$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::LessEqual,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 1,
+                    associativity: Associativity::Left,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^>=$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::GreaterEqual,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 1,
+                    associativity: Associativity::Left,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^<$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::Less,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 1,
+                    associativity: Associativity::Left,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^>$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::Greater,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 1,
+                    associativity: Associativity::Left,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^=$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::Assign,
+                    value: 0f64,
+                    name: String::new(),
+                    precedence: 0,
+                    associativity: Associativity::Right,
+                    span: span.clone(),
                 })
             } else if Regex::new(r"^-?\d+\.?\d*$").unwrap().is_match(content) {
                 Ok(Token {
                     r#type: TokenType::Number,
                     value: content.parse().expect("number required"),
+                    name: content.to_string(),
                     precedence: 0,
                     associativity: Associativity::Right,
+                    span: span.clone(),
                 })
             } else if Regex::new(r"^\($").unwrap().is_match(content){
                  Ok(Token {
                     r#type: TokenType::OpeningParenthesis,
                     value: 0f64,
+                    name: String::new(),
                     precedence: 0,
                     associativity: Associativity::Right,
+                    span: span.clone(),
                 })
              } else if Regex::new(r"^\)$").unwrap().is_match(content){
                  Ok(Token {
                     r#type: TokenType::ClosingParaenthesis,
                     value: 0f64,
+                    name: String::new(),
+                    precedence: 0,
+                    associativity: Associativity::Right,
+                    span: span.clone(),
+                })
+            } else if Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap().is_match(content) {
+                Ok(Token {
+                    r#type: TokenType::Identifier,
+                    value: 0f64,
+                    name: content.to_string(),
                     precedence: 0,
                     associativity: Associativity::Right,
-                })    
+                    span: span.clone(),
+                })
             } else {
-                let error = format!("Error creating new token with content: {}", content);
-                Err(Box::leak(error.into_boxed_str()))
+                Err(CalcError::UnknownToken { text: content.to_string(), span })
+            }
+        }
+
+        pub fn function(name: &str, span: Range<usize>) -> Token {
+            Token {
+                r#type: TokenType::Function,
+                value: 0f64,
+                name: name.to_string(),
+                precedence: 5,
+                associativity: Associativity::Right,
+                span,
             }
         }
 
@@ -180,69 +480,658 @@ pub mod lexer {
         pub fn get_type(&self) -> TokenType {
             self.r#type
         }
+        pub fn get_name(&self) -> &str {
+            &self.name
+        }
         pub fn get_precedence(&self) -> u8 {
             self.precedence
         }
         pub fn get_associativity(&self) -> Associativity {
             self.associativity
         }
+        pub fn get_span(&self) -> Range<usize> {
+            self.span.clone()
+        }
+    }
+}
+
+pub mod rational {
+    #[derive(Debug, PartialEq, Copy, Clone)]
+    pub struct Rational {
+        pub numerator: i128,
+        pub denominator: i128,
+    }
+
+    fn gcd(a: i128, b: i128) -> i128 {
+        if b == 0 { a.abs() } else { gcd(b, a % b) }
+    }
+
+    impl Rational {
+        pub fn new(numerator: i128, denominator: i128) -> Rational {
+            let g = gcd(numerator, denominator).max(1);
+            let (numerator, denominator) = if denominator < 0 {
+                (-numerator / g, -denominator / g)
+            } else {
+                (numerator / g, denominator / g)
+            };
+            Rational { numerator, denominator }
+        }
+
+        pub fn from_str(text: &str) -> Rational {
+            match text.split_once('.') {
+                Some((whole, fraction)) => {
+                    let denominator = 10i128.pow(fraction.len() as u32);
+                    let numerator: i128 = format!("{}{}", whole, fraction).parse().unwrap();
+                    Rational::new(numerator, denominator)
+                }
+                None => Rational::new(text.parse().unwrap(), 1),
+            }
+        }
+
+        /// Every op below uses checked `i128` arithmetic and errors on overflow instead of
+        /// panicking (debug) or wrapping (release) on ordinary-looking but huge operands,
+        /// mirroring how `calculator::combine_ints` guards `Value::Int` arithmetic. Unlike
+        /// `Value`, `Rational` has no float variant to silently promote to without breaking
+        /// the exactness `evaluate_exact` promises, so overflow is reported as an error.
+        pub fn add(self, other: Rational) -> Result<Rational, &'static str> {
+            let numerator = self
+                .numerator
+                .checked_mul(other.denominator)
+                .zip(other.numerator.checked_mul(self.denominator))
+                .and_then(|(a, b)| a.checked_add(b));
+            let denominator = self.denominator.checked_mul(other.denominator);
+            match (numerator, denominator) {
+                (Some(numerator), Some(denominator)) => Ok(Rational::new(numerator, denominator)),
+                _ => Err("Overflow"),
+            }
+        }
+
+        pub fn sub(self, other: Rational) -> Result<Rational, &'static str> {
+            let numerator = self
+                .numerator
+                .checked_mul(other.denominator)
+                .zip(other.numerator.checked_mul(self.denominator))
+                .and_then(|(a, b)| a.checked_sub(b));
+            let denominator = self.denominator.checked_mul(other.denominator);
+            match (numerator, denominator) {
+                (Some(numerator), Some(denominator)) => Ok(Rational::new(numerator, denominator)),
+                _ => Err("Overflow"),
+            }
+        }
+
+        pub fn mul(self, other: Rational) -> Result<Rational, &'static str> {
+            let numerator = self.numerator.checked_mul(other.numerator);
+            let denominator = self.denominator.checked_mul(other.denominator);
+            match (numerator, denominator) {
+                (Some(numerator), Some(denominator)) => Ok(Rational::new(numerator, denominator)),
+                _ => Err("Overflow"),
+            }
+        }
+
+        pub fn div(self, other: Rational) -> Result<Rational, &'static str> {
+            if other.numerator == 0 {
+                return Err("Division by zero");
+            }
+            let numerator = self.numerator.checked_mul(other.denominator);
+            let denominator = self.denominator.checked_mul(other.numerator);
+            match (numerator, denominator) {
+                (Some(numerator), Some(denominator)) => Ok(Rational::new(numerator, denominator)),
+                _ => Err("Overflow"),
+            }
+        }
+
+        pub fn pow(self, exponent: i128) -> Result<Rational, &'static str> {
+            if exponent == 0 {
+                return Ok(Rational::new(1, 1));
+            } else if exponent > 0 {
+                let exact = u32::try_from(exponent)
+                    .ok()
+                    .and_then(|exp| self.numerator.checked_pow(exp).zip(self.denominator.checked_pow(exp)));
+                match exact {
+                    Some((numerator, denominator)) => Ok(Rational::new(numerator, denominator)),
+                    None => Err("Overflow"),
+                }
+            } else {
+                if self.numerator == 0 {
+                    return Err("Division by zero");
+                }
+                let inverted = Rational::new(self.denominator, self.numerator);
+                inverted.pow(-exponent)
+            }
+        }
+
+        pub fn to_f64(self) -> f64 {
+            self.numerator as f64 / self.denominator as f64
+        }
+
+        pub fn from_f64(value: f64) -> Rational {
+            Rational::from_str(&format!("{:.10}", value))
+        }
     }
 }
 
 pub mod calculator {
+    use crate::parser::error::CalcError;
     use crate::parser::lexer::*;
+    use crate::parser::rational::Rational;
+    use std::collections::HashMap;
+
+    pub fn default_environment() -> HashMap<String, f64> {
+        let mut env = HashMap::new();
+        env.insert("pi".to_string(), std::f64::consts::PI);
+        env.insert("e".to_string(), std::f64::consts::E);
+        env
+    }
+
+    pub fn evaluate(expression: &String) -> Result<f64, CalcError> {
+        evaluate_with(expression, &default_environment())
+    }
+
+    pub fn evaluate_with(expression: &String, env: &HashMap<String, f64>) -> Result<f64, CalcError> {
+        match tokenize(expression) {
+            Ok(v) => match shunting_yard(&v) {
+                Ok(s) => calculate(&s, env),
+                Err(x) => Err(x),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// A reusable evaluation environment that remembers variable assignments across calls.
+    pub struct Context {
+        bindings: HashMap<String, f64>,
+    }
+
+    impl Context {
+        pub fn new() -> Context {
+            Context { bindings: default_environment() }
+        }
+
+        pub fn get(&self, name: &str) -> Option<f64> {
+            self.bindings.get(name).copied()
+        }
+
+        pub fn set(&mut self, name: &str, value: f64) {
+            self.bindings.insert(name.to_string(), value);
+        }
+    }
+
+    impl Default for Context {
+        fn default() -> Self {
+            Context::new()
+        }
+    }
+
+    /// Evaluate `expression` against `ctx`. An expression of the form `name = value` assigns
+    /// the evaluated right-hand side to `name` in `ctx` instead of just returning it.
+    pub fn evaluate_in(expression: &str, ctx: &mut Context) -> Result<f64, CalcError> {
+        let tokens = tokenize(&expression.to_string())?;
+        match tokens.iter().position(|t| t.get_type() == TokenType::Assign) {
+            Some(assign_pos) => {
+                let (lhs, rest) = tokens.split_at(assign_pos);
+                let rhs = &rest[1..];
+                if lhs.len() != 1 || lhs[0].get_type() != TokenType::Identifier {
+                    return Err(CalcError::Message(
+                        "left-hand side of an assignment must be a single variable name".to_string(),
+                    ));
+                }
+                let value = calculate(&shunting_yard(rhs)?, &ctx.bindings)?;
+                ctx.set(lhs[0].get_name(), value);
+                Ok(value)
+            }
+            None => calculate(&shunting_yard(&tokens)?, &ctx.bindings),
+        }
+    }
+
+    /// A computed result, keeping integer expressions exact instead of always widening to `f64`.
+    #[derive(Debug, PartialEq, Copy, Clone)]
+    pub enum Value {
+        Int(i64),
+        Float(f64),
+        Boolean(bool),
+    }
+
+    impl Value {
+        fn as_f64(&self) -> Option<f64> {
+            match self {
+                Value::Int(i) => Some(*i as f64),
+                Value::Float(f) => Some(*f),
+                Value::Boolean(_) => None,
+            }
+        }
+    }
+
+    /// The `Value` a number token contributes: `Int` for literals without a decimal point,
+    /// `Float` otherwise.
+    fn number_value(t: &Token) -> Value {
+        if t.get_name().contains('.') {
+            Value::Float(t.get_value())
+        } else {
+            Value::Int(t.get_value() as i64)
+        }
+    }
+
+    /// Combine two `i64` operands, falling back to `Float` when the exact integer result
+    /// would overflow `i64` instead of panicking (debug) or silently wrapping (release).
+    fn combine_ints(op: TokenType, a: i64, b: i64) -> Result<Value, CalcError> {
+        let to_float = || Value::Float(match op {
+            TokenType::Plus => a as f64 + b as f64,
+            TokenType::Minus => a as f64 - b as f64,
+            TokenType::Multiply => a as f64 * b as f64,
+            TokenType::Divide => a as f64 / b as f64,
+            TokenType::Power => (a as f64).powf(b as f64),
+            _ => unreachable!("combine_ints only handles arithmetic operators"),
+        });
+
+        match op {
+            TokenType::Plus => Ok(a.checked_add(b).map_or_else(to_float, Value::Int)),
+            TokenType::Minus => Ok(a.checked_sub(b).map_or_else(to_float, Value::Int)),
+            TokenType::Multiply => Ok(a.checked_mul(b).map_or_else(to_float, Value::Int)),
+            TokenType::Divide => {
+                if b == 0 {
+                    Err(CalcError::DivisionByZero)
+                } else if a.checked_rem(b) == Some(0) {
+                    Ok(a.checked_div(b).map_or_else(to_float, Value::Int))
+                } else {
+                    Ok(to_float())
+                }
+            }
+            TokenType::Power => {
+                if b < 0 {
+                    Ok(to_float())
+                } else {
+                    Ok(u32::try_from(b).ok().and_then(|exp| a.checked_pow(exp)).map_or_else(to_float, Value::Int))
+                }
+            }
+            _ => Err(CalcError::Message("can not have number on operation stack".to_string())),
+        }
+    }
+
+    fn combine_values(op: TokenType, l: Value, r: Value) -> Result<Value, CalcError> {
+        if let (Value::Int(a), Value::Int(b)) = (l, r) {
+            return combine_ints(op, a, b);
+        }
+
+        let (l, r) = match (l.as_f64(), r.as_f64()) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return Err(CalcError::Message("arithmetic operators require numeric operands".to_string())),
+        };
+        let result = match op {
+            TokenType::Divide => l / r,
+            TokenType::Multiply => l * r,
+            TokenType::Minus => l - r,
+            TokenType::Plus => l + r,
+            TokenType::Power => l.powf(r),
+            _ => return Err(CalcError::Message("can not have number on operation stack".to_string())),
+        };
+        Ok(Value::Float(result))
+    }
 
-    pub fn evaluate(expression: &String) -> Result<f64, &'static str> {
+    pub fn evaluate_value(expression: &String) -> Result<Value, CalcError> {
+        evaluate_value_with(expression, &default_environment())
+    }
+
+    pub fn evaluate_value_with(expression: &String, env: &HashMap<String, f64>) -> Result<Value, CalcError> {
         match tokenize(expression) {
             Ok(v) => match shunting_yard(&v) {
-                Ok(s) => calculate(&s),
+                Ok(s) => calculate_value(&s, env),
                 Err(x) => Err(x),
             },
             Err(e) => Err(e),
         }
     }
 
-    fn calculate(tokens: &Vec<Token>) -> Result<f64, &'static str> {
+    fn calculate_value(tokens: &[Token], env: &HashMap<String, f64>) -> Result<Value, CalcError> {
+        let mut stack: Vec<Value> = Vec::new();
+        for t in tokens.iter() {
+            if t.get_type() == TokenType::Number {
+                stack.push(number_value(t));
+            } else if t.get_type() == TokenType::Identifier {
+                match env.get(t.get_name()) {
+                    Some(value) => stack.push(Value::Float(*value)),
+                    None => return Err(CalcError::Message(format!("unknown identifier '{}'", t.get_name()))),
+                }
+            } else if t.is_operator() {
+                let r_operand = stack.pop();
+                let l_operand = stack.pop();
+                match (l_operand, r_operand) {
+                    (Some(l), Some(r)) => stack.push(combine_values(t.get_type(), l, r)?),
+                    _ => return Err(CalcError::Message("arithmetic operators require numeric operands".to_string())),
+                }
+            } else if t.is_comparison() {
+                let r_operand = stack.pop();
+                let l_operand = stack.pop();
+                match (l_operand.as_ref().and_then(Value::as_f64), r_operand.as_ref().and_then(Value::as_f64)) {
+                    (Some(l), Some(r)) => {
+                        let result = match t.get_type() {
+                            TokenType::Equal => l == r,
+                            TokenType::NotEqual => l != r,
+                            TokenType::Less => l < r,
+                            TokenType::LessEqual => l <= r,
+                            TokenType::Greater => l > r,
+                            TokenType::GreaterEqual => l >= r,
+                            _ => return Err(CalcError::Message("can not have number on operation stack".to_string())),
+                        };
+                        stack.push(Value::Boolean(result));
+                    }
+                    _ => return Err(CalcError::Message("comparison operators require numeric operands".to_string())),
+                }
+            } else if t.get_type() == TokenType::Function {
+                let arity = function_arity(t.get_name())?;
+                if stack.len() < arity {
+                    return Err(CalcError::Message("missing function argument".to_string()));
+                }
+                let mut args: Vec<f64> = Vec::with_capacity(arity);
+                for value in stack.split_off(stack.len() - arity) {
+                    match value.as_f64() {
+                        Some(n) => args.push(n),
+                        None => return Err(CalcError::Message("functions require numeric arguments".to_string())),
+                    }
+                }
+                let result = apply_function(t.get_name(), &args)?;
+                stack.push(Value::Float(result));
+            }
+        }
+
+        if stack.len() == 1usize {
+            Ok(stack[0])
+        } else {
+            Err(CalcError::Message("unable to evaluate expression".to_string()))
+        }
+    }
+
+    pub fn evaluate_exact(expression: &String) -> Result<Rational, CalcError> {
+        match tokenize(expression) {
+            Ok(v) => match shunting_yard(&v) {
+                Ok(s) => calculate_exact(&s),
+                Err(x) => Err(x),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Maps a `Rational` arithmetic failure onto the matching `CalcError`, keeping "division
+    /// by zero" distinct from an exact-arithmetic overflow so callers see an accurate message.
+    fn rational_error_to_calc_error(e: &'static str) -> CalcError {
+        match e {
+            "Division by zero" => CalcError::DivisionByZero,
+            _ => CalcError::Message("exact arithmetic overflowed".to_string()),
+        }
+    }
+
+    fn calculate_exact(tokens: &[Token]) -> Result<Rational, CalcError> {
+        let mut processing_numbers: Vec<Rational> = Vec::new();
+        for t in tokens.iter() {
+            if t.get_type() == TokenType::Number {
+                processing_numbers.push(Rational::from_str(t.get_name()));
+            }
+            if t.is_operator() {
+                let r_operand = processing_numbers.pop();
+                let l_operand = processing_numbers.pop();
+                if let (Some(l), Some(r)) = (l_operand, r_operand) {
+                    let result = match t.get_type() {
+                        TokenType::Plus => l.add(r).map_err(rational_error_to_calc_error)?,
+                        TokenType::Minus => l.sub(r).map_err(rational_error_to_calc_error)?,
+                        TokenType::Multiply => l.mul(r).map_err(rational_error_to_calc_error)?,
+                        TokenType::Divide => l.div(r).map_err(rational_error_to_calc_error)?,
+                        TokenType::Power => {
+                            if r.denominator == 1 {
+                                l.pow(r.numerator).map_err(rational_error_to_calc_error)?
+                            } else {
+                                let approx = l.to_f64().powf(r.to_f64());
+                                if !approx.is_finite() {
+                                    return Err(CalcError::Message("non-integer power does not yield a real number".to_string()));
+                                }
+                                Rational::from_f64(approx)
+                            }
+                        }
+                        _ => return Err(CalcError::Message("can not have number on operation stack".to_string())),
+                    };
+                    processing_numbers.push(result);
+                }
+            } else if t.get_type() == TokenType::Identifier || t.get_type() == TokenType::Function {
+                return Err(CalcError::Message("variables and functions are not supported in exact mode".to_string()));
+            }
+        }
+
+        if processing_numbers.len() == 1usize {
+            Ok(processing_numbers[0])
+        } else {
+            Err(CalcError::Message("unable to evaluate expression".to_string()))
+        }
+    }
+
+    /// The solution(s) of a single-variable polynomial equation found via `solve`.
+    #[derive(Debug, PartialEq, Clone)]
+    pub enum Solution {
+        NoSolution,
+        AllReals,
+        Root(f64),
+        Roots(f64, f64),
+        DoubleRoot(f64),
+        Complex { real: f64, imaginary: f64 },
+    }
+
+    /// Solve a single-variable equation of degree at most 2, e.g. `2*x+3=7` or `x^2=4`.
+    pub fn solve(equation: &str) -> Result<Solution, CalcError> {
+        let tokens = tokenize(&equation.to_string())?;
+        let assign_positions: Vec<usize> = tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.get_type() == TokenType::Assign)
+            .map(|(i, _)| i)
+            .collect();
+        if assign_positions.len() != 1 {
+            return Err(CalcError::Message("an equation must contain exactly one '='".to_string()));
+        }
+        let idx = assign_positions[0];
+        let (lhs, rest) = tokens.split_at(idx);
+        let rhs = &rest[1..];
+
+        let variable = find_variable(&tokens)?;
+        let env = default_environment();
+
+        let left_poly = evaluate_polynomial(&shunting_yard(lhs)?, &variable, &env)?;
+        let right_poly = evaluate_polynomial(&shunting_yard(rhs)?, &variable, &env)?;
+
+        let mut coefficients = poly_sub(&left_poly, &right_poly);
+        while coefficients.len() > 1 && *coefficients.last().unwrap() == 0.0 {
+            coefficients.pop();
+        }
+
+        match coefficients.len() {
+            1 => {
+                if coefficients[0] == 0.0 {
+                    Ok(Solution::AllReals)
+                } else {
+                    Ok(Solution::NoSolution)
+                }
+            }
+            2 => {
+                let (b, a) = (coefficients[0], coefficients[1]);
+                Ok(Solution::Root(-b / a))
+            }
+            3 => {
+                let (c, b, a) = (coefficients[0], coefficients[1], coefficients[2]);
+                let discriminant = b * b - 4.0 * a * c;
+                if discriminant > 0.0 {
+                    let sqrt_d = discriminant.sqrt();
+                    Ok(Solution::Roots((-b + sqrt_d) / (2.0 * a), (-b - sqrt_d) / (2.0 * a)))
+                } else if discriminant == 0.0 {
+                    Ok(Solution::DoubleRoot(-b / (2.0 * a)))
+                } else {
+                    let sqrt_d = (-discriminant).sqrt();
+                    Ok(Solution::Complex { real: -b / (2.0 * a), imaginary: sqrt_d / (2.0 * a) })
+                }
+            }
+            _ => Err(CalcError::Message("cannot solve polynomials of degree greater than 2".to_string())),
+        }
+    }
+
+    fn find_variable(tokens: &[Token]) -> Result<String, CalcError> {
+        let mut names: Vec<&str> = Vec::new();
+        for t in tokens.iter() {
+            if t.get_type() == TokenType::Identifier && t.get_name() != "pi" && t.get_name() != "e" && !names.contains(&t.get_name()) {
+                names.push(t.get_name());
+            }
+        }
+        match names.len() {
+            0 => Err(CalcError::Message("no variable to solve for".to_string())),
+            1 => Ok(names[0].to_string()),
+            _ => Err(CalcError::Message("can only solve equations with a single variable".to_string())),
+        }
+    }
+
+    fn poly_add(a: &[f64], b: &[f64]) -> Vec<f64> {
+        let len = a.len().max(b.len());
+        (0..len).map(|i| a.get(i).copied().unwrap_or(0.0) + b.get(i).copied().unwrap_or(0.0)).collect()
+    }
+
+    fn poly_sub(a: &[f64], b: &[f64]) -> Vec<f64> {
+        let len = a.len().max(b.len());
+        (0..len).map(|i| a.get(i).copied().unwrap_or(0.0) - b.get(i).copied().unwrap_or(0.0)).collect()
+    }
+
+    fn poly_mul(a: &[f64], b: &[f64]) -> Vec<f64> {
+        let mut result = vec![0.0; a.len() + b.len() - 1];
+        for (i, &ai) in a.iter().enumerate() {
+            for (j, &bj) in b.iter().enumerate() {
+                result[i + j] += ai * bj;
+            }
+        }
+        result
+    }
+
+    fn evaluate_polynomial(tokens: &[Token], variable: &str, env: &HashMap<String, f64>) -> Result<Vec<f64>, CalcError> {
+        let mut stack: Vec<Vec<f64>> = Vec::new();
+        for t in tokens.iter() {
+            if t.get_type() == TokenType::Number {
+                stack.push(vec![t.get_value()]);
+            } else if t.get_type() == TokenType::Identifier {
+                if t.get_name() == variable {
+                    stack.push(vec![0.0, 1.0]);
+                } else if let Some(value) = env.get(t.get_name()) {
+                    stack.push(vec![*value]);
+                } else {
+                    return Err(CalcError::Message(format!("unknown identifier '{}'", t.get_name())));
+                }
+            } else if t.is_operator() {
+                let r = stack.pop();
+                let l = stack.pop();
+                if let (Some(l), Some(r)) = (l, r) {
+                    let result = match t.get_type() {
+                        TokenType::Plus => poly_add(&l, &r),
+                        TokenType::Minus => poly_sub(&l, &r),
+                        TokenType::Multiply => poly_mul(&l, &r),
+                        TokenType::Divide => {
+                            if r.len() != 1 || r[0] == 0.0 {
+                                return Err(CalcError::Message("can only divide by a nonzero constant".to_string()));
+                            }
+                            l.iter().map(|c| c / r[0]).collect()
+                        }
+                        TokenType::Power => {
+                            if r.len() != 1 || r[0].fract() != 0.0 || r[0] < 0.0 {
+                                return Err(CalcError::Message("exponents must be non-negative integer constants".to_string()));
+                            }
+                            let exponent = r[0] as u32;
+                            let mut result = vec![1.0];
+                            for _ in 0..exponent {
+                                result = poly_mul(&result, &l);
+                            }
+                            result
+                        }
+                        _ => return Err(CalcError::Message("unsupported operator in equation".to_string())),
+                    };
+                    stack.push(result);
+                }
+            } else {
+                return Err(CalcError::Message("functions and comparisons are not supported in equations".to_string()));
+            }
+        }
+
+        if stack.len() == 1 {
+            Ok(stack.pop().unwrap())
+        } else {
+            Err(CalcError::Message("unable to evaluate equation".to_string()))
+        }
+    }
+
+    fn function_arity(name: &str) -> Result<usize, CalcError> {
+        match name {
+            "sqrt" | "sin" | "cos" | "tan" | "ln" | "abs" => Ok(1),
+            "max" | "min" => Ok(2),
+            _ => Err(CalcError::Message(format!("unknown function '{}'", name))),
+        }
+    }
+
+    fn apply_function(name: &str, args: &[f64]) -> Result<f64, CalcError> {
+        match name {
+            "sqrt" => Ok(args[0].sqrt()),
+            "sin" => Ok(args[0].sin()),
+            "cos" => Ok(args[0].cos()),
+            "tan" => Ok(args[0].tan()),
+            "ln" => Ok(args[0].ln()),
+            "abs" => Ok(args[0].abs()),
+            "max" => Ok(args[0].max(args[1])),
+            "min" => Ok(args[0].min(args[1])),
+            _ => Err(CalcError::Message(format!("unknown function '{}'", name))),
+        }
+    }
+
+    fn calculate(tokens: &[Token], env: &HashMap<String, f64>) -> Result<f64, CalcError> {
         let mut processing_numbers: Vec<f64> = Vec::new();
         for t in tokens.iter() {
             if t.get_type() == TokenType::Number {
                 processing_numbers.push(t.get_value());
+            } else if t.get_type() == TokenType::Identifier {
+                match env.get(t.get_name()) {
+                    Some(value) => processing_numbers.push(*value),
+                    None => return Err(CalcError::Message(format!("unknown identifier '{}'", t.get_name()))),
+                }
             }
             if t.is_operator() {
                 let r_operand = processing_numbers.pop();
                 let l_operand = processing_numbers.pop();
-                if r_operand.is_some() && l_operand.is_some() {
+                if let (Some(l_operand), Some(r_operand)) = (l_operand, r_operand) {
                     let result: f64 = match t.get_type() {
-                        TokenType::Divide => l_operand.unwrap() / r_operand.unwrap(),
-                        TokenType::Multiply => l_operand.unwrap() * r_operand.unwrap(),
-                        TokenType::Minus => l_operand.unwrap() - r_operand.unwrap(),
-                        TokenType::Plus => l_operand.unwrap() + r_operand.unwrap(),
-                        TokenType::Power => l_operand.unwrap().powf(r_operand.unwrap()),
-                        _ => return Err("Can not have number on operation stack"),
+                        TokenType::Divide => l_operand / r_operand,
+                        TokenType::Multiply => l_operand * r_operand,
+                        TokenType::Minus => l_operand - r_operand,
+                        TokenType::Plus => l_operand + r_operand,
+                        TokenType::Power => l_operand.powf(r_operand),
+                        _ => return Err(CalcError::Message("can not have number on operation stack".to_string())),
                     };
                     processing_numbers.push(result);
                 }
+            } else if t.get_type() == TokenType::Function {
+                let arity = function_arity(t.get_name())?;
+                if processing_numbers.len() < arity {
+                    return Err(CalcError::Message("missing function argument".to_string()));
+                }
+                let args: Vec<f64> = processing_numbers.split_off(processing_numbers.len() - arity);
+                let result = apply_function(t.get_name(), &args)?;
+                processing_numbers.push(result);
             }
         }
 
         if processing_numbers.len() == 1usize {
             Ok(processing_numbers[0])
         } else {
-            Err("Error parsing expresion")
+            Err(CalcError::Message("unable to evaluate expression".to_string()))
         }
     }
 
-    fn shunting_yard(tokens: &Vec<Token>) -> Result<Vec<Token>, &'static str> {
+    fn shunting_yard(tokens: &[Token]) -> Result<Vec<Token>, CalcError> {
         let mut reverse_notation: Vec<Token> = Vec::new();
         let mut stack: Vec<Token> = Vec::new();
 
         for t in tokens.iter() {
-            if t.get_type() == TokenType::Number {
-                reverse_notation.push(*t);
+            if t.get_type() == TokenType::Number || t.get_type() == TokenType::Identifier {
+                reverse_notation.push(t.clone());
             }else if t.get_type() == TokenType::OpeningParenthesis{
-                stack.push(*t);
+                stack.push(t.clone());
             }else if t.get_type() == TokenType::ClosingParaenthesis{
-                while !stack.is_empty() && stack.last().unwrap().get_type() != TokenType::OpeningParenthesis 
+                while !stack.is_empty() && stack.last().unwrap().get_type() != TokenType::OpeningParenthesis
                 {
                     reverse_notation.push(stack.pop().unwrap());
                 }
@@ -250,9 +1139,22 @@ pub mod calculator {
                 {
                     stack.pop();
                 }else{
-                    return Err("Expected opening bracket");
+                    return Err(CalcError::MismatchedParen { span: t.get_span() });
                 }
-            } else if t.is_operator() {
+                if !stack.is_empty() && stack.last().unwrap().get_type() == TokenType::Function {
+                    reverse_notation.push(stack.pop().unwrap());
+                }
+            } else if t.get_type() == TokenType::Function {
+                stack.push(t.clone());
+            } else if t.get_type() == TokenType::Comma {
+                while !stack.is_empty() && stack.last().unwrap().get_type() != TokenType::OpeningParenthesis
+                {
+                    reverse_notation.push(stack.pop().unwrap());
+                }
+                if stack.is_empty() {
+                    return Err(CalcError::Message(format!("argument separator outside of function call at position {}", t.get_span().start)));
+                }
+            } else if t.is_operator() || t.is_comparison() {
                 while !stack.is_empty()
                     && (stack.last().unwrap().get_precedence() > t.get_precedence()
                         || (stack.last().unwrap().get_precedence() == t.get_precedence()
@@ -260,11 +1162,14 @@ pub mod calculator {
                 {
                     reverse_notation.push(stack.pop().unwrap());
                 }
-                stack.push(*t);
+                stack.push(t.clone());
             }
         }
-        while !stack.is_empty() {
-            reverse_notation.push(stack.pop().unwrap());
+        while let Some(t) = stack.pop() {
+            if t.get_type() == TokenType::OpeningParenthesis {
+                return Err(CalcError::MismatchedParen { span: t.get_span() });
+            }
+            reverse_notation.push(t);
         }
 
         Ok(reverse_notation)
@@ -279,7 +1184,7 @@ mod lexer_tests {
     #[test]
     fn valid_plus() {
         let plus: String = String::from("+");
-        let token = Token::new(&plus).unwrap();
+        let token = Token::new(&plus, 0..1).unwrap();
 
         assert_eq!(token.get_associativity(), Associativity::Left);
         assert_eq!(token.get_precedence(), 2);
@@ -289,7 +1194,7 @@ mod lexer_tests {
     #[test]
     fn valid_minus() {
         let minus: String = String::from("-");
-        let token = Token::new(&minus).unwrap();
+        let token = Token::new(&minus, 0..1).unwrap();
 
         assert_eq!(token.get_associativity(), Associativity::Left);
         assert_eq!(token.get_precedence(), 2);
@@ -299,7 +1204,7 @@ mod lexer_tests {
     #[test]
     fn valid_multiply() {
         let mult: String = String::from("*");
-        let token = Token::new(&mult).unwrap();
+        let token = Token::new(&mult, 0..1).unwrap();
 
         assert_eq!(token.get_associativity(), Associativity::Left);
         assert_eq!(token.get_precedence(), 3);
@@ -308,7 +1213,7 @@ mod lexer_tests {
     #[test]
     fn valid_divide() {
         let div: String = String::from("/");
-        let token = Token::new(&div).unwrap();
+        let token = Token::new(&div, 0..1).unwrap();
 
         assert_eq!(token.get_associativity(), Associativity::Left);
         assert_eq!(token.get_precedence(), 3);
@@ -318,7 +1223,7 @@ mod lexer_tests {
     #[test]
     fn valid_power() {
         let pow: String = String::from("^");
-        let token = Token::new(&pow).unwrap();
+        let token = Token::new(&pow, 0..1).unwrap();
 
         assert_eq!(token.get_associativity(), Associativity::Right);
         assert_eq!(token.get_precedence(), 4);
@@ -328,7 +1233,7 @@ mod lexer_tests {
     #[test]
     fn valid_int() {
         let c: String = String::from("324");
-        let token = Token::new(&c).unwrap();
+        let token = Token::new(&c, 0..1).unwrap();
 
         assert_eq!(token.get_type(), TokenType::Number);
         assert_eq!(token.get_value(), 324f64);
@@ -337,7 +1242,7 @@ mod lexer_tests {
     #[test]
     fn valid_float_short() {
         let c: String = String::from("324.");
-        let token = Token::new(&c).unwrap();
+        let token = Token::new(&c, 0..1).unwrap();
 
         assert_eq!(token.get_type(), TokenType::Number);
         assert_eq!(token.get_value(), 324f64);
@@ -346,7 +1251,7 @@ mod lexer_tests {
     #[test]
     fn valid_float() {
         let c: String = String::from("324.34532342");
-        let token = Token::new(&c).unwrap();
+        let token = Token::new(&c, 0..1).unwrap();
 
         assert_eq!(token.get_type(), TokenType::Number);
         assert_eq!(token.get_value(), 324.34532342f64);
@@ -355,7 +1260,7 @@ mod lexer_tests {
     #[test]
     fn negative_number() {
         let c: String = String::from("-324.34532342");
-        let token = Token::new(&c).unwrap();
+        let token = Token::new(&c, 0..1).unwrap();
 
         assert_eq!(token.get_type(), TokenType::Number);
         assert_eq!(token.get_value(), -324.34532342f64);
@@ -363,11 +1268,123 @@ mod lexer_tests {
 
     #[test]
     fn invalid() {
-        let c: String = String::from("sfnwo");
-        let token = Token::new(&c);
+        let c: String = String::from("$%");
+        let token = Token::new(&c, 0..1);
         assert!(token.is_err());
     }
 
+    #[test]
+    fn unknown_token_reports_position() {
+        let err = tokenize(&String::from("2+$")).unwrap_err();
+        assert_eq!(err, crate::parser::error::CalcError::UnknownToken { text: "$".to_string(), span: 2..3 });
+    }
+
+    #[test]
+    fn valid_identifier() {
+        let c: String = String::from("pi");
+        let token = Token::new(&c, 0..1).unwrap();
+
+        assert_eq!(token.get_type(), TokenType::Identifier);
+        assert_eq!(token.get_name(), "pi");
+    }
+
+    #[test]
+    fn identifier_in_expression() {
+        let v = tokenize(&String::from("r^2*pi")).unwrap();
+        assert_eq!(v[0].get_type(), TokenType::Identifier);
+        assert_eq!(v[0].get_name(), "r");
+        assert_eq!(v[1].get_type(), TokenType::Power);
+        assert_eq!(v[2].get_type(), TokenType::Number);
+        assert_eq!(v[3].get_type(), TokenType::Multiply);
+        assert_eq!(v[4].get_type(), TokenType::Identifier);
+        assert_eq!(v[4].get_name(), "pi");
+    }
+
+    #[test]
+    fn function_call() {
+        let v = tokenize(&String::from("sqrt(2)")).unwrap();
+        assert_eq!(v[0].get_type(), TokenType::Function);
+        assert_eq!(v[0].get_name(), "sqrt");
+        assert_eq!(v[1].get_type(), TokenType::OpeningParenthesis);
+        assert_eq!(v[2].get_type(), TokenType::Number);
+        assert_eq!(v[2].get_value(), 2.0);
+        assert_eq!(v[3].get_type(), TokenType::ClosingParaenthesis);
+    }
+
+    #[test]
+    fn function_call_with_multiple_arguments() {
+        let v = tokenize(&String::from("max(2,5)")).unwrap();
+        assert_eq!(v[0].get_type(), TokenType::Function);
+        assert_eq!(v[0].get_name(), "max");
+        assert_eq!(v[2].get_type(), TokenType::Number);
+        assert_eq!(v[2].get_value(), 2.0);
+        assert_eq!(v[3].get_type(), TokenType::Comma);
+        assert_eq!(v[4].get_type(), TokenType::Number);
+        assert_eq!(v[4].get_value(), 5.0);
+    }
+
+    #[test]
+    fn implicit_multiplication_number_identifier() {
+        let v = tokenize(&String::from("2pi")).unwrap();
+        assert_eq!(v[0].get_type(), TokenType::Number);
+        assert_eq!(v[1].get_type(), TokenType::Multiply);
+        assert_eq!(v[2].get_type(), TokenType::Identifier);
+        assert_eq!(v[2].get_name(), "pi");
+    }
+
+    #[test]
+    fn implicit_multiplication_number_parenthesis() {
+        let v = tokenize(&String::from("3(4+1)")).unwrap();
+        assert_eq!(v[0].get_type(), TokenType::Number);
+        assert_eq!(v[1].get_type(), TokenType::Multiply);
+        assert_eq!(v[2].get_type(), TokenType::OpeningParenthesis);
+    }
+
+    #[test]
+    fn implicit_multiplication_adjacent_parentheses() {
+        let v = tokenize(&String::from("(1+2)(3+4)")).unwrap();
+        assert_eq!(v[4].get_type(), TokenType::ClosingParaenthesis);
+        assert_eq!(v[5].get_type(), TokenType::Multiply);
+        assert_eq!(v[6].get_type(), TokenType::OpeningParenthesis);
+    }
+
+    #[test]
+    fn implicit_multiplication_identifier_then_parenthesis() {
+        let v = tokenize(&String::from("pi(2+3)")).unwrap();
+        assert_eq!(v[0].get_type(), TokenType::Identifier);
+        assert_eq!(v[0].get_name(), "pi");
+        assert_eq!(v[1].get_type(), TokenType::Multiply);
+        assert_eq!(v[2].get_type(), TokenType::OpeningParenthesis);
+    }
+
+    #[test]
+    fn known_function_name_still_tokenizes_as_a_function_call() {
+        let v = tokenize(&String::from("sqrt(4)")).unwrap();
+        assert_eq!(v[0].get_type(), TokenType::Function);
+        assert_eq!(v[0].get_name(), "sqrt");
+    }
+
+    #[test]
+    fn comparison_operators() {
+        let v = tokenize(&String::from("2==2")).unwrap();
+        assert_eq!(v[1].get_type(), TokenType::Equal);
+
+        let v = tokenize(&String::from("2!=2")).unwrap();
+        assert_eq!(v[1].get_type(), TokenType::NotEqual);
+
+        let v = tokenize(&String::from("2<2")).unwrap();
+        assert_eq!(v[1].get_type(), TokenType::Less);
+
+        let v = tokenize(&String::from("2<=2")).unwrap();
+        assert_eq!(v[1].get_type(), TokenType::LessEqual);
+
+        let v = tokenize(&String::from("2>2")).unwrap();
+        assert_eq!(v[1].get_type(), TokenType::Greater);
+
+        let v = tokenize(&String::from("2>=2")).unwrap();
+        assert_eq!(v[1].get_type(), TokenType::GreaterEqual);
+    }
+
     #[test]
     fn single_num() {
         let v = tokenize(&String::from("123")).unwrap();
@@ -545,9 +1562,67 @@ mod lexer_tests {
 
 }
 
+#[cfg(test)]
+mod rational_tests {
+    use crate::parser::rational::Rational;
+
+    #[test]
+    fn reduces_to_lowest_terms() {
+        let r = Rational::new(4, 8);
+        assert_eq!(r, Rational::new(1, 2));
+    }
+
+    #[test]
+    fn keeps_sign_in_numerator() {
+        let r = Rational::new(3, -4);
+        assert_eq!(r.numerator, -3);
+        assert_eq!(r.denominator, 4);
+    }
+
+    #[test]
+    fn parses_decimal_literals() {
+        assert_eq!(Rational::from_str("324.34"), Rational::new(32434, 100));
+        assert_eq!(Rational::from_str("3"), Rational::new(3, 1));
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(Rational::new(1, 3).add(Rational::new(1, 6)).unwrap(), Rational::new(1, 2));
+        assert_eq!(Rational::new(1, 2).sub(Rational::new(1, 3)).unwrap(), Rational::new(1, 6));
+        assert_eq!(Rational::new(2, 3).mul(Rational::new(3, 4)).unwrap(), Rational::new(1, 2));
+        assert_eq!(Rational::new(1, 2).div(Rational::new(1, 4)).unwrap(), Rational::new(2, 1));
+    }
+
+    #[test]
+    fn division_by_zero_errors() {
+        assert!(Rational::new(1, 2).div(Rational::new(0, 1)).is_err());
+    }
+
+    #[test]
+    fn integer_powers() {
+        assert_eq!(Rational::new(2, 3).pow(2).unwrap(), Rational::new(4, 9));
+        assert_eq!(Rational::new(2, 3).pow(-1).unwrap(), Rational::new(3, 2));
+    }
+
+    #[test]
+    fn from_f64_approximates_the_value() {
+        let r = Rational::from_f64(0.5);
+        assert_eq!(r, Rational::new(1, 2));
+    }
+
+    #[test]
+    fn overflow_errors_instead_of_panicking() {
+        let huge = Rational::new(99999999999999999999, 1);
+        assert!(huge.mul(huge).is_err());
+        assert!(huge.pow(3).is_err());
+    }
+}
+
 #[cfg(test)]
 mod calculator_tests {
     use crate::parser::calculator::*;
+    use crate::parser::error::CalcError;
+    use crate::parser::rational::Rational;
 
     #[test]
     fn addition() {
@@ -595,4 +1670,251 @@ mod calculator_tests {
         assert_eq!(evaluate(&"18/3/2".to_string()).unwrap(), 3.0);
         assert_eq!(evaluate(&"2^2^3".to_string()).unwrap(), 256.0);
     }
+
+    #[test]
+    fn brackets() {
+        assert_eq!(evaluate(&"2*(3+4)".to_string()).unwrap(), 14.0);
+        assert_eq!(evaluate(&"(2+3)*(4+5)".to_string()).unwrap(), 45.0);
+        assert_eq!(evaluate(&"(12+(3-(2*2)))".to_string()).unwrap(), 11.0);
+    }
+
+    #[test]
+    fn mismatched_parenthesis() {
+        let err = evaluate(&"(1+2".to_string()).unwrap_err();
+        assert_eq!(err, CalcError::MismatchedParen { span: 0..1 });
+        assert_eq!(err.span(), Some(0..1));
+    }
+
+    #[test]
+    fn stray_closing_parenthesis_points_at_itself() {
+        let err = evaluate(&"1+2)".to_string()).unwrap_err();
+        assert_eq!(err.span(), Some(3..4));
+    }
+
+    #[test]
+    fn renders_a_caret_underneath_the_offending_span() {
+        let err = evaluate(&"2+$".to_string()).unwrap_err();
+        assert_eq!(err.render("2+$"), "2+$\n  ^\nunknown token '$' at position 2");
+    }
+
+    #[test]
+    fn empty_expression_errors() {
+        assert_eq!(evaluate(&"".to_string()).unwrap_err(), CalcError::EmptyExpression);
+    }
+
+    #[test]
+    fn unexpected_end_errors() {
+        assert_eq!(evaluate(&"2+".to_string()).unwrap_err(), CalcError::UnexpectedEnd);
+    }
+
+    #[test]
+    fn default_constants() {
+        assert_eq!(evaluate(&"2*pi".to_string()).unwrap(), 2.0 * std::f64::consts::PI);
+        assert_eq!(evaluate(&"e".to_string()).unwrap(), std::f64::consts::E);
+    }
+
+    #[test]
+    fn custom_environment() {
+        let mut env = default_environment();
+        env.insert("r".to_string(), 3.0);
+        assert_eq!(evaluate_with(&"r^2*pi".to_string(), &env).unwrap(), 9.0 * std::f64::consts::PI);
+    }
+
+    #[test]
+    fn unknown_identifier() {
+        assert!(evaluate(&"2*unknown".to_string()).is_err());
+    }
+
+    #[test]
+    fn variable_assignment_and_reuse() {
+        let mut ctx = Context::new();
+        assert_eq!(evaluate_in("x = 3+4", &mut ctx).unwrap(), 7.0);
+        assert_eq!(evaluate_in("x * 2", &mut ctx).unwrap(), 14.0);
+        assert_eq!(ctx.get("x"), Some(7.0));
+    }
+
+    #[test]
+    fn assignment_can_reference_other_variables() {
+        let mut ctx = Context::new();
+        evaluate_in("r = 3", &mut ctx).unwrap();
+        assert_eq!(evaluate_in("area = r^2*pi", &mut ctx).unwrap(), 9.0 * std::f64::consts::PI);
+    }
+
+    #[test]
+    fn undefined_variable_in_context_errors() {
+        let mut ctx = Context::new();
+        assert!(evaluate_in("x * 2", &mut ctx).is_err());
+    }
+
+    #[test]
+    fn assignment_target_must_be_a_plain_identifier() {
+        let mut ctx = Context::new();
+        assert!(evaluate_in("1+2 = 3", &mut ctx).is_err());
+    }
+
+    #[test]
+    fn unary_functions() {
+        assert_eq!(evaluate(&"sqrt(4)".to_string()).unwrap(), 2.0);
+        assert_eq!(evaluate(&"sin(0)".to_string()).unwrap(), 0.0);
+        assert_eq!(evaluate(&"abs(-3)".to_string()).unwrap(), 3.0);
+        assert_eq!(evaluate(&"tan(0)".to_string()).unwrap(), 0.0);
+        assert_eq!(evaluate(&"ln(1)".to_string()).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn binary_functions() {
+        assert_eq!(evaluate(&"max(2,5)".to_string()).unwrap(), 5.0);
+        assert_eq!(evaluate(&"min(2,5)".to_string()).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn nested_function_call() {
+        assert_eq!(evaluate(&"sqrt(abs(-16))".to_string()).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn wrong_function_arity() {
+        assert!(evaluate(&"max(2)".to_string()).is_err());
+    }
+
+    #[test]
+    fn unknown_function() {
+        assert!(evaluate(&"foo(2)".to_string()).is_err());
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        assert_eq!(evaluate(&"2pi".to_string()).unwrap(), 2.0 * std::f64::consts::PI);
+        assert_eq!(evaluate(&"3(4+1)".to_string()).unwrap(), 15.0);
+        assert_eq!(evaluate(&"(1+2)(3+4)".to_string()).unwrap(), 21.0);
+    }
+
+    #[test]
+    fn implicit_multiplication_identifier_then_parenthesis() {
+        assert_eq!(evaluate(&"pi(2+3)".to_string()).unwrap(), std::f64::consts::PI * 5.0);
+
+        let mut ctx = Context::new();
+        evaluate_in("r = 3", &mut ctx).unwrap();
+        assert_eq!(evaluate_in("r(2+3)", &mut ctx).unwrap(), 15.0);
+    }
+
+    #[test]
+    fn exact_avoids_float_drift() {
+        let r = evaluate_exact(&"0.1+0.2".to_string()).unwrap();
+        assert_eq!(r.numerator, 3);
+        assert_eq!(r.denominator, 10);
+    }
+
+    #[test]
+    fn exact_division_stays_exact() {
+        let r = evaluate_exact(&"1/3*3".to_string()).unwrap();
+        assert_eq!(r.numerator, 1);
+        assert_eq!(r.denominator, 1);
+    }
+
+    #[test]
+    fn exact_division_by_zero() {
+        assert!(evaluate_exact(&"1/0".to_string()).is_err());
+    }
+
+    #[test]
+    fn exact_non_integer_power_falls_back_to_float_precision() {
+        let r = evaluate_exact(&"4^0.5".to_string()).unwrap();
+        assert_eq!(r, Rational::new(2, 1));
+    }
+
+    #[test]
+    fn exact_negative_base_fractional_power_errors() {
+        assert!(evaluate_exact(&"(-1)^0.5".to_string()).is_err());
+    }
+
+    #[test]
+    fn exact_overflow_errors_instead_of_panicking() {
+        assert!(evaluate_exact(&"99999999999999999999*99999999999999999999".to_string()).is_err());
+        assert!(evaluate_exact(&"99999999999999999999^3".to_string()).is_err());
+    }
+
+    #[test]
+    fn comparisons_produce_booleans() {
+        assert_eq!(evaluate_value(&"2+2==4".to_string()).unwrap(), Value::Boolean(true));
+        assert_eq!(evaluate_value(&"3*2>5".to_string()).unwrap(), Value::Boolean(true));
+        assert_eq!(evaluate_value(&"3<=3".to_string()).unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn arithmetic_still_produces_numbers() {
+        assert_eq!(evaluate_value(&"2+5".to_string()).unwrap(), Value::Int(7));
+    }
+
+    #[test]
+    fn comparison_operand_type_error() {
+        assert!(evaluate_value(&"(2==2)+1".to_string()).is_err());
+    }
+
+    #[test]
+    fn integer_division_stays_int_when_it_divides_evenly() {
+        assert_eq!(evaluate_value(&"6/2".to_string()).unwrap(), Value::Int(3));
+    }
+
+    #[test]
+    fn integer_division_promotes_to_float_otherwise() {
+        assert_eq!(evaluate_value(&"6/4".to_string()).unwrap(), Value::Float(1.5));
+    }
+
+    #[test]
+    fn any_float_operand_promotes_the_result() {
+        assert_eq!(evaluate_value(&"2+0.5".to_string()).unwrap(), Value::Float(2.5));
+        assert_eq!(evaluate_value(&"2*pi".to_string()).unwrap(), Value::Float(2.0 * std::f64::consts::PI));
+    }
+
+    #[test]
+    fn negative_integer_exponent_promotes_to_float() {
+        assert_eq!(evaluate_value(&"2^-1".to_string()).unwrap(), Value::Float(0.5));
+    }
+
+    #[test]
+    fn integer_division_by_zero_errors() {
+        assert!(evaluate_value(&"1/0".to_string()).is_err());
+    }
+
+    #[test]
+    fn integer_overflow_promotes_to_float_instead_of_panicking() {
+        assert_eq!(evaluate_value(&"2^100".to_string()).unwrap(), Value::Float(2f64.powf(100.0)));
+        assert_eq!(
+            evaluate_value(&"99999999999^99999999999".to_string()).unwrap(),
+            Value::Float(99999999999f64.powf(99999999999f64))
+        );
+    }
+
+    #[test]
+    fn solves_linear_equations() {
+        assert_eq!(solve("2*x+3=7").unwrap(), Solution::Root(2.0));
+        assert_eq!(solve("x=5").unwrap(), Solution::Root(5.0));
+    }
+
+    #[test]
+    fn solves_quadratic_equations_with_two_roots() {
+        assert_eq!(solve("x^2-5*x+6=0").unwrap(), Solution::Roots(3.0, 2.0));
+    }
+
+    #[test]
+    fn solves_quadratic_equations_with_a_double_root() {
+        assert_eq!(solve("x^2-4*x+4=0").unwrap(), Solution::DoubleRoot(2.0));
+    }
+
+    #[test]
+    fn solves_quadratic_equations_with_complex_roots() {
+        assert_eq!(solve("x^2+1=0").unwrap(), Solution::Complex { real: 0.0, imaginary: 1.0 });
+    }
+
+    #[test]
+    fn degenerate_equations() {
+        assert_eq!(solve("x-x=0").unwrap(), Solution::AllReals);
+        assert_eq!(solve("0*x=1").unwrap(), Solution::NoSolution);
+    }
+
+    #[test]
+    fn cannot_solve_above_degree_two() {
+        assert!(solve("x^3=8").is_err());
+    }
 }